@@ -0,0 +1,25 @@
+#![no_main]
+
+use bytes::Bytes;
+use fuel_core_p2p::{
+    codecs::{
+        postcard::PostcardCodec,
+        request_response::RequestResponseMessageHandler,
+    },
+    request_response::protocols::RequestResponseProtocol,
+};
+use libfuzzer_sys::fuzz_target;
+use std::num::NonZeroU32;
+
+const MAX_RESPONSE_SIZE: NonZeroU32 = NonZeroU32::new(1024 * 1024).unwrap();
+
+// Feeds arbitrary bytes into `read_response` under the `V1` protocol to prove
+// that no peer-controlled input can panic or OOM the decoder, mirroring the
+// deserialize-block/script/transaction fuzz harnesses in rust-bitcoin.
+fuzz_target!(|data: &[u8]| {
+    let mut codec: RequestResponseMessageHandler<PostcardCodec> =
+        RequestResponseMessageHandler::new(MAX_RESPONSE_SIZE);
+    let _ = futures::executor::block_on(
+        codec.read_response(&RequestResponseProtocol::V1, Bytes::copy_from_slice(data)),
+    );
+});
@@ -0,0 +1,25 @@
+#![no_main]
+
+use bytes::Bytes;
+use fuel_core_p2p::{
+    codecs::{
+        postcard::PostcardCodec,
+        request_response::RequestResponseMessageHandler,
+    },
+    request_response::protocols::RequestResponseProtocol,
+};
+use libfuzzer_sys::fuzz_target;
+use std::num::NonZeroU32;
+
+const MAX_RESPONSE_SIZE: NonZeroU32 = NonZeroU32::new(1024 * 1024).unwrap();
+
+// Same as `read_response_v1`, but against the `V2` protocol, whose responses
+// carry the `Vec<SealedBlockHeader>` / `Vec<Option<NetworkableTransactionPool>>`
+// collections that `PostcardCodec::decode_bounded` guards in production.
+fuzz_target!(|data: &[u8]| {
+    let mut codec: RequestResponseMessageHandler<PostcardCodec> =
+        RequestResponseMessageHandler::new(MAX_RESPONSE_SIZE);
+    let _ = futures::executor::block_on(
+        codec.read_response(&RequestResponseProtocol::V2, Bytes::copy_from_slice(data)),
+    );
+});
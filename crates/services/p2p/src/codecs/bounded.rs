@@ -0,0 +1,667 @@
+//! A `serde` [`Deserializer`](serde::Deserializer) wrapper that rejects an
+//! oversized collection or byte string as soon as its length prefix is read,
+//! before the real `Visitor` gets a chance to call `Vec::with_capacity`/
+//! `String::with_capacity` on it. Checking collection sizes only after a
+//! plain `decode` would still let a peer make the node allocate whatever it
+//! likes; this wrapper is what actually prevents that.
+
+use std::io;
+
+/// Limits enforced while decoding, derived from the configured
+/// `max_response_size` rather than hardcoded, since a response can never
+/// legitimately contain more elements or a larger embedded transaction than
+/// would fit in `max_response_size` bytes.
+#[derive(Clone, Copy, Debug)]
+pub struct DecodeLimits {
+    /// Upper bound on the number of elements any single sequence or map in
+    /// the decoded value may declare.
+    pub max_elements: usize,
+    /// Upper bound on the length of any single byte string (e.g. a
+    /// transaction embedded in a response) in the decoded value.
+    pub max_nested_bytes: usize,
+}
+
+impl DecodeLimits {
+    pub fn from_max_response_size(max_response_size: std::num::NonZeroU32) -> Self {
+        let max_response_size = max_response_size.get() as usize;
+        Self {
+            max_elements: max_response_size,
+            max_nested_bytes: max_response_size,
+        }
+    }
+}
+
+/// Which of [`DecodeLimits`]' two limits was violated.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LimitKind {
+    Elements,
+    NestedBytes,
+}
+
+/// Distinguishes a bound violation from an ordinary malformed-postcard error, so
+/// callers can tell a peer that misbehaved from one that is merely incompatible.
+#[derive(Debug)]
+pub enum BoundedDecodeError {
+    Decode(io::Error),
+    LimitExceeded {
+        kind: LimitKind,
+        limit: usize,
+        actual: usize,
+    },
+}
+
+impl From<BoundedDecodeError> for io::Error {
+    fn from(error: BoundedDecodeError) -> Self {
+        match error {
+            BoundedDecodeError::Decode(e) => e,
+            BoundedDecodeError::LimitExceeded {
+                kind,
+                limit,
+                actual,
+            } => io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("decoded {kind:?} is {actual}, exceeding the limit of {limit}"),
+            ),
+        }
+    }
+}
+
+/// Deserializes `T` from `bytes`, rejecting any sequence, map, or byte string
+/// whose declared length exceeds `limits` *before* it is allocated.
+///
+/// Deliberately does not also reject based on `bytes.len()`: for a `V3` frame
+/// this is called on the already-decompressed body, which can legitimately be
+/// much larger than the compressed bytes that arrived on the wire. The
+/// structural checks below are what actually bound memory use, regardless of
+/// how large the encoded form is.
+pub fn decode_bounded<'de, T>(
+    bytes: &'de [u8],
+    limits: &DecodeLimits,
+) -> Result<T, BoundedDecodeError>
+where
+    T: serde::Deserialize<'de>,
+{
+    let mut deserializer = postcard::Deserializer::from_bytes(bytes);
+    T::deserialize(LimitingDeserializer {
+        inner: &mut deserializer,
+        limits,
+    })
+    .map_err(|e| BoundedDecodeError::Decode(io::Error::new(io::ErrorKind::Other, e.to_string())))
+}
+
+struct LimitingDeserializer<'a, D> {
+    inner: D,
+    limits: &'a DecodeLimits,
+}
+
+macro_rules! forward_deserialize {
+    ($($method:ident),* $(,)?) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: serde::de::Visitor<'de>,
+            {
+                self.inner.$method(LimitingVisitor {
+                    inner: visitor,
+                    limits: self.limits,
+                })
+            }
+        )*
+    };
+}
+
+impl<'de, 'a, D> serde::Deserializer<'de> for LimitingDeserializer<'a, D>
+where
+    D: serde::Deserializer<'de>,
+{
+    type Error = D::Error;
+
+    forward_deserialize!(
+        deserialize_any,
+        deserialize_bool,
+        deserialize_i8,
+        deserialize_i16,
+        deserialize_i32,
+        deserialize_i64,
+        deserialize_i128,
+        deserialize_u8,
+        deserialize_u16,
+        deserialize_u32,
+        deserialize_u64,
+        deserialize_u128,
+        deserialize_f32,
+        deserialize_f64,
+        deserialize_char,
+        deserialize_str,
+        deserialize_string,
+        deserialize_bytes,
+        deserialize_byte_buf,
+        deserialize_option,
+        deserialize_unit,
+        deserialize_seq,
+        deserialize_map,
+        deserialize_identifier,
+        deserialize_ignored_any,
+    );
+
+    fn deserialize_unit_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.inner.deserialize_unit_struct(
+            name,
+            LimitingVisitor {
+                inner: visitor,
+                limits: self.limits,
+            },
+        )
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.inner.deserialize_newtype_struct(
+            name,
+            LimitingVisitor {
+                inner: visitor,
+                limits: self.limits,
+            },
+        )
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.inner.deserialize_tuple(
+            len,
+            LimitingVisitor {
+                inner: visitor,
+                limits: self.limits,
+            },
+        )
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.inner.deserialize_tuple_struct(
+            name,
+            len,
+            LimitingVisitor {
+                inner: visitor,
+                limits: self.limits,
+            },
+        )
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.inner.deserialize_struct(
+            name,
+            fields,
+            LimitingVisitor {
+                inner: visitor,
+                limits: self.limits,
+            },
+        )
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.inner.deserialize_enum(
+            name,
+            variants,
+            LimitingVisitor {
+                inner: visitor,
+                limits: self.limits,
+            },
+        )
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+struct LimitingVisitor<'a, V> {
+    inner: V,
+    limits: &'a DecodeLimits,
+}
+
+macro_rules! forward_visit {
+    ($($method:ident: $ty:ty),* $(,)?) => {
+        $(
+            fn $method<E>(self, v: $ty) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                self.inner.$method(v)
+            }
+        )*
+    };
+}
+
+impl<'de, 'a, V> serde::de::Visitor<'de> for LimitingVisitor<'a, V>
+where
+    V: serde::de::Visitor<'de>,
+{
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.inner.expecting(formatter)
+    }
+
+    forward_visit!(
+        visit_bool: bool,
+        visit_i8: i8,
+        visit_i16: i16,
+        visit_i32: i32,
+        visit_i64: i64,
+        visit_i128: i128,
+        visit_u8: u8,
+        visit_u16: u16,
+        visit_u32: u32,
+        visit_u64: u64,
+        visit_u128: u128,
+        visit_f32: f32,
+        visit_f64: f64,
+        visit_char: char,
+        visit_str: &str,
+        visit_borrowed_str: &'de str,
+        visit_string: String,
+    );
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        self.inner.visit_unit()
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        self.inner.visit_none()
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        self.inner.visit_some(LimitingDeserializer {
+            inner: deserializer,
+            limits: self.limits,
+        })
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        self.inner.visit_newtype_struct(LimitingDeserializer {
+            inner: deserializer,
+            limits: self.limits,
+        })
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        if v.len() > self.limits.max_nested_bytes {
+            return Err(E::custom(format!(
+                "byte string of length {} exceeds the limit of {}",
+                v.len(),
+                self.limits.max_nested_bytes
+            )));
+        }
+        self.inner.visit_bytes(v)
+    }
+
+    fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        if v.len() > self.limits.max_nested_bytes {
+            return Err(E::custom(format!(
+                "byte string of length {} exceeds the limit of {}",
+                v.len(),
+                self.limits.max_nested_bytes
+            )));
+        }
+        self.inner.visit_borrowed_bytes(v)
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        if v.len() > self.limits.max_nested_bytes {
+            return Err(E::custom(format!(
+                "byte string of length {} exceeds the limit of {}",
+                v.len(),
+                self.limits.max_nested_bytes
+            )));
+        }
+        self.inner.visit_byte_buf(v)
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        if let Some(hint) = seq.size_hint() {
+            if hint > self.limits.max_elements {
+                return Err(serde::de::Error::custom(format!(
+                    "sequence declares {hint} elements, exceeding the limit of {}",
+                    self.limits.max_elements
+                )));
+            }
+        }
+        self.inner.visit_seq(LimitingSeqAccess {
+            inner: seq,
+            limits: self.limits,
+        })
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        if let Some(hint) = map.size_hint() {
+            if hint > self.limits.max_elements {
+                return Err(serde::de::Error::custom(format!(
+                    "map declares {hint} entries, exceeding the limit of {}",
+                    self.limits.max_elements
+                )));
+            }
+        }
+        self.inner.visit_map(LimitingMapAccess {
+            inner: map,
+            limits: self.limits,
+        })
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::EnumAccess<'de>,
+    {
+        self.inner.visit_enum(LimitingEnumAccess {
+            inner: data,
+            limits: self.limits,
+        })
+    }
+}
+
+struct LimitingSeed<'a, T> {
+    inner: T,
+    limits: &'a DecodeLimits,
+}
+
+impl<'de, 'a, T> serde::de::DeserializeSeed<'de> for LimitingSeed<'a, T>
+where
+    T: serde::de::DeserializeSeed<'de>,
+{
+    type Value = T::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        self.inner.deserialize(LimitingDeserializer {
+            inner: deserializer,
+            limits: self.limits,
+        })
+    }
+}
+
+struct LimitingSeqAccess<'a, A> {
+    inner: A,
+    limits: &'a DecodeLimits,
+}
+
+impl<'de, 'a, A> serde::de::SeqAccess<'de> for LimitingSeqAccess<'a, A>
+where
+    A: serde::de::SeqAccess<'de>,
+{
+    type Error = A::Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        self.inner.next_element_seed(LimitingSeed {
+            inner: seed,
+            limits: self.limits,
+        })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+struct LimitingMapAccess<'a, A> {
+    inner: A,
+    limits: &'a DecodeLimits,
+}
+
+impl<'de, 'a, A> serde::de::MapAccess<'de> for LimitingMapAccess<'a, A>
+where
+    A: serde::de::MapAccess<'de>,
+{
+    type Error = A::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: serde::de::DeserializeSeed<'de>,
+    {
+        self.inner.next_key_seed(LimitingSeed {
+            inner: seed,
+            limits: self.limits,
+        })
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::DeserializeSeed<'de>,
+    {
+        self.inner.next_value_seed(LimitingSeed {
+            inner: seed,
+            limits: self.limits,
+        })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+struct LimitingEnumAccess<'a, A> {
+    inner: A,
+    limits: &'a DecodeLimits,
+}
+
+impl<'de, 'a, A> serde::de::EnumAccess<'de> for LimitingEnumAccess<'a, A>
+where
+    A: serde::de::EnumAccess<'de>,
+{
+    type Error = A::Error;
+    type Variant = LimitingVariantAccess<'a, A::Variant>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: serde::de::DeserializeSeed<'de>,
+    {
+        let (value, variant) = self.inner.variant_seed(LimitingSeed {
+            inner: seed,
+            limits: self.limits,
+        })?;
+        Ok((
+            value,
+            LimitingVariantAccess {
+                inner: variant,
+                limits: self.limits,
+            },
+        ))
+    }
+}
+
+struct LimitingVariantAccess<'a, A> {
+    inner: A,
+    limits: &'a DecodeLimits,
+}
+
+impl<'de, 'a, A> serde::de::VariantAccess<'de> for LimitingVariantAccess<'a, A>
+where
+    A: serde::de::VariantAccess<'de>,
+{
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        self.inner.unit_variant()
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: serde::de::DeserializeSeed<'de>,
+    {
+        self.inner.newtype_variant_seed(LimitingSeed {
+            inner: seed,
+            limits: self.limits,
+        })
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.inner.tuple_variant(
+            len,
+            LimitingVisitor {
+                inner: visitor,
+                limits: self.limits,
+            },
+        )
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        self.inner.struct_variant(
+            fields,
+            LimitingVisitor {
+                inner: visitor,
+                limits: self.limits,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use super::*;
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Nested {
+        values: Vec<u32>,
+    }
+
+    #[test]
+    fn decode_bounded__rejects_sequence_whose_declared_length_exceeds_max_elements() {
+        let value = Nested {
+            values: vec![1, 2, 3, 4],
+        };
+        let bytes = postcard::to_allocvec(&value).unwrap();
+        let limits = DecodeLimits {
+            max_elements: 3,
+            max_nested_bytes: bytes.len(),
+        };
+
+        let result: Result<Nested, _> = decode_bounded(&bytes, &limits);
+
+        assert!(matches!(
+            result,
+            Err(BoundedDecodeError::LimitExceeded {
+                kind: LimitKind::Elements,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn decode_bounded__rejects_byte_string_whose_declared_length_exceeds_max_nested_bytes() {
+        #[derive(serde::Serialize, serde::Deserialize, Debug)]
+        struct WithBytes<'a> {
+            #[serde(borrow)]
+            payload: &'a [u8],
+        }
+        let owned = vec![0u8; 32];
+        let value = WithBytes { payload: &owned };
+        let bytes = postcard::to_allocvec(&value).unwrap();
+        let limits = DecodeLimits {
+            max_elements: bytes.len(),
+            max_nested_bytes: 16,
+        };
+
+        let result: Result<WithBytes, _> = decode_bounded(&bytes, &limits);
+
+        assert!(matches!(
+            result,
+            Err(BoundedDecodeError::LimitExceeded {
+                kind: LimitKind::NestedBytes,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn decode_bounded__accepts_value_within_limits() {
+        let value = Nested {
+            values: vec![1, 2, 3, 4],
+        };
+        let bytes = postcard::to_allocvec(&value).unwrap();
+        let limits = DecodeLimits::from_max_response_size(std::num::NonZeroU32::new(1024).unwrap());
+
+        let decoded: Nested = decode_bounded(&bytes, &limits).expect("value within limits");
+
+        assert_eq!(decoded, value);
+    }
+}
@@ -0,0 +1,148 @@
+//! `PostcardCodec` prefixes every `V3` response body with a small fixed-size
+//! header (see [`super::postcard::PostcardCodec::encode_for_protocol`]) so the
+//! reader knows the body's length and whether it's compressed before it reads
+//! a single byte of it. `V1`/`V2` stay headerless for backwards compatibility.
+
+use std::io;
+
+/// Wire size of [`MessageHeader`]: a 4-byte big-endian length, then three
+/// single-byte fields.
+pub const MESSAGE_HEADER_LENGTH: usize = 7;
+
+/// No single frame body may legitimately exceed this size; frames advertising
+/// more are rejected by [`MessageHeader::from`]. `read_response` receives the
+/// whole frame already buffered, so there the check runs before the body is
+/// decompressed or decoded, not before the frame itself is buffered.
+pub const MESSAGE_LENGTH_MAX: u32 = 16 * 1024 * 1024;
+
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MessageType {
+    Request = 0,
+    Response = 1,
+    Data = 2,
+}
+
+impl TryFrom<u8> for MessageType {
+    type Error = io::Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(MessageType::Request),
+            1 => Ok(MessageType::Response),
+            2 => Ok(MessageType::Data),
+            other => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unknown message type `{other}`"),
+            )),
+        }
+    }
+}
+
+/// Bitfield carried in [`MessageHeader::flags`].
+pub mod flags {
+    /// The body is zstd-compressed (see `RequestResponseProtocol::V3`).
+    pub const COMPRESSED: u8 = 1 << 0;
+    /// The frame intentionally carries an empty body.
+    pub const NO_DATA: u8 = 1 << 1;
+    /// The last frame of a chunked/streamed response.
+    pub const STREAM_END: u8 = 1 << 2;
+}
+
+/// Fixed-size frame header written ahead of every `V3` postcard body.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MessageHeader {
+    pub length: u32,
+    pub protocol_version: u8,
+    pub message_type: MessageType,
+    pub flags: u8,
+}
+
+impl MessageHeader {
+    pub fn new(length: u32, protocol_version: u8, message_type: MessageType, flags: u8) -> Self {
+        Self {
+            length,
+            protocol_version,
+            message_type,
+            flags,
+        }
+    }
+
+    /// Parses a [`MessageHeader`] from the first [`MESSAGE_HEADER_LENGTH`] bytes
+    /// of `bytes`, rejecting truncated headers, unknown message types, and
+    /// lengths above [`MESSAGE_LENGTH_MAX`] before the body is looked at.
+    pub fn from(bytes: &[u8]) -> Result<Self, io::Error> {
+        if bytes.len() < MESSAGE_HEADER_LENGTH {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "frame header is truncated",
+            ));
+        }
+
+        let length = u32::from_be_bytes(bytes[0..4].try_into().expect(
+            "slice has exactly 4 bytes",
+        ));
+        if length > MESSAGE_LENGTH_MAX {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("frame length {length} exceeds MESSAGE_LENGTH_MAX ({MESSAGE_LENGTH_MAX})"),
+            ));
+        }
+
+        let protocol_version = bytes[4];
+        let message_type = MessageType::try_from(bytes[5])?;
+        let flags = bytes[6];
+
+        Ok(Self {
+            length,
+            protocol_version,
+            message_type,
+            flags,
+        })
+    }
+}
+
+impl From<MessageHeader> for Vec<u8> {
+    fn from(header: MessageHeader) -> Self {
+        let mut out = Vec::with_capacity(MESSAGE_HEADER_LENGTH);
+        out.extend_from_slice(&header.length.to_be_bytes());
+        out.push(header.protocol_version);
+        out.push(header.message_type as u8);
+        out.push(header.flags);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_header__roundtrips_through_from_and_into_vec() {
+        let header = MessageHeader::new(1234, 3, MessageType::Response, flags::COMPRESSED);
+        let bytes: Vec<u8> = header.into();
+        let decoded = MessageHeader::from(&bytes).expect("valid header should parse");
+        assert_eq!(decoded, header);
+    }
+
+    #[test]
+    fn message_header__rejects_truncated_bytes() {
+        let header = MessageHeader::new(1, 1, MessageType::Request, 0);
+        let bytes: Vec<u8> = header.into();
+        let truncated = &bytes[..MESSAGE_HEADER_LENGTH - 1];
+        assert!(MessageHeader::from(truncated).is_err());
+    }
+
+    #[test]
+    fn message_header__rejects_length_above_max() {
+        let header = MessageHeader::new(MESSAGE_LENGTH_MAX + 1, 1, MessageType::Request, 0);
+        let bytes: Vec<u8> = header.into();
+        assert!(MessageHeader::from(&bytes).is_err());
+    }
+
+    #[test]
+    fn message_header__rejects_unknown_message_type() {
+        let bytes = vec![0, 0, 0, 0, 1, 0xff, 0];
+        assert!(MessageHeader::from(&bytes).is_err());
+    }
+}
@@ -0,0 +1,7 @@
+/// Encodes/decodes gossipsub (pub/sub) payloads, as opposed to
+/// [`super::request_response::RequestResponseMessageHandler`] which handles the
+/// direct request/response protocol.
+#[derive(Clone, Default)]
+pub struct GossipsubMessageHandler<Codec> {
+    pub codec: Codec,
+}
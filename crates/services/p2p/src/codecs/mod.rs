@@ -0,0 +1,26 @@
+pub mod bounded;
+pub mod frame;
+pub mod gossipsub;
+pub mod postcard;
+pub mod request_response;
+
+/// Serializes `T` into a byte buffer borrowed from (or owned alongside) `value`.
+pub trait Encode<T>
+where
+    T: ?Sized,
+{
+    type Encoder<'a>: AsRef<[u8]>
+    where
+        T: 'a,
+        Self: 'a;
+    type Error;
+
+    fn encode<'a>(&self, value: &'a T) -> Result<Self::Encoder<'a>, Self::Error>;
+}
+
+/// Deserializes a `T` from a byte slice.
+pub trait Decode<T> {
+    type Error;
+
+    fn decode(&self, bytes: &[u8]) -> Result<T, Self::Error>;
+}
@@ -1,19 +1,143 @@
 use super::{
     Decode,
     Encode,
+    bounded,
+    frame::{
+        self,
+        MessageHeader,
+        MessageType,
+    },
     gossipsub::GossipsubMessageHandler,
     request_response::RequestResponseMessageHandler,
 };
 
+pub use bounded::{
+    BoundedDecodeError,
+    DecodeLimits,
+    LimitKind,
+};
+
 use std::{
     borrow::Cow,
-    io,
+    io::{
+        self,
+        Read,
+    },
     num::NonZeroU32,
 };
 
+/// Below this size, compressing a postcard-encoded frame costs more in CPU time
+/// than it saves in bytes on the wire, so `V3` frames are left raw.
+const COMPRESSION_THRESHOLD_BYTES: usize = 1024;
+
+/// Protocol version advertised in every `V3` [`MessageHeader`].
+const V3_HEADER_PROTOCOL_VERSION: u8 = 3;
+
 #[derive(Clone, Default)]
 pub struct PostcardCodec;
 
+impl PostcardCodec {
+    /// Postcard-encodes `value` and, when the negotiated protocol is `V3`,
+    /// prefixes it with a [`MessageHeader`] describing its length and whether
+    /// it was zstd-compressed (only worthwhile above
+    /// [`COMPRESSION_THRESHOLD_BYTES`]). `V1`/`V2` peers keep receiving the
+    /// bare postcard bytes they always have, with no header at all.
+    pub(crate) fn encode_for_protocol<T>(
+        &self,
+        protocol: &super::super::request_response::protocols::RequestResponseProtocol,
+        value: &T,
+    ) -> Result<Vec<u8>, io::Error>
+    where
+        T: ?Sized + serde::Serialize,
+    {
+        use super::super::request_response::protocols::RequestResponseProtocol;
+
+        let encoded = postcard::to_allocvec(value)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+
+        if !matches!(protocol, RequestResponseProtocol::V3) {
+            return Ok(encoded);
+        }
+
+        let (body, flags) = if encoded.len() <= COMPRESSION_THRESHOLD_BYTES {
+            (encoded, 0u8)
+        } else {
+            let compressed = zstd::stream::encode_all(encoded.as_slice(), 0)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+            (compressed, frame::flags::COMPRESSED)
+        };
+
+        let length = u32::try_from(body.len()).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidData, "V3 frame body exceeds u32::MAX")
+        })?;
+        let header = MessageHeader::new(
+            length,
+            V3_HEADER_PROTOCOL_VERSION,
+            MessageType::Response,
+            flags,
+        );
+
+        let mut framed: Vec<u8> = header.into();
+        framed.extend_from_slice(&body);
+        Ok(framed)
+    }
+
+    /// Strips and validates a `V3` [`MessageHeader`] off the front of `bytes`
+    /// and returns the postcard body it describes, decompressing it first if
+    /// `flags::COMPRESSED` is set. `V1`/`V2` frames carry no header, so `bytes`
+    /// is returned unchanged.
+    ///
+    /// `max_decompressed_size` bounds how much a compressed body is allowed to
+    /// expand to: zstd's own `decode_all` has no output limit, so a peer could
+    /// otherwise send a tiny, well-under-`MESSAGE_LENGTH_MAX` frame that
+    /// expands to hundreds of megabytes before `decode_bounded`'s element/byte
+    /// checks ever get a chance to run. Rejecting that here, during
+    /// decompression itself, is what actually caps it.
+    pub(crate) fn body_for_protocol<'a>(
+        &self,
+        protocol: &super::super::request_response::protocols::RequestResponseProtocol,
+        bytes: &'a [u8],
+        max_decompressed_size: NonZeroU32,
+    ) -> Result<Cow<'a, [u8]>, io::Error> {
+        use super::super::request_response::protocols::RequestResponseProtocol;
+
+        if !matches!(protocol, RequestResponseProtocol::V3) {
+            return Ok(Cow::Borrowed(bytes));
+        }
+
+        let header = MessageHeader::from(bytes)?;
+        let body = &bytes[frame::MESSAGE_HEADER_LENGTH..];
+        if body.len() as u64 != header.length as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "V3 frame body length does not match its header",
+            ));
+        }
+
+        if header.flags & frame::flags::COMPRESSED != 0 {
+            let max_decompressed_size = u64::from(max_decompressed_size.get());
+            let decoder = zstd::stream::Decoder::new(body)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+            let mut decompressed = Vec::new();
+            decoder
+                .take(max_decompressed_size + 1)
+                .read_to_end(&mut decompressed)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+            if decompressed.len() as u64 > max_decompressed_size {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "V3 frame decompresses to more than max_decompressed_size ({max_decompressed_size} bytes)"
+                    ),
+                ));
+            }
+            Ok(Cow::Owned(decompressed))
+        } else {
+            Ok(Cow::Borrowed(body))
+        }
+    }
+}
+
 impl RequestResponseMessageHandler<PostcardCodec> {
     pub fn new(max_block_size: NonZeroU32) -> Self {
         Self {
@@ -60,9 +184,60 @@ where
     }
 }
 
+impl PostcardCodec {
+    /// Like [`Decode::decode`], but deserializes borrowing from `bytes` instead of
+    /// allocating a new owned value for every `Vec`/`String`/byte slice in `T`.
+    ///
+    /// This would be the right way to decode `V2ResponseMessage::TxPoolFullTransactions`
+    /// without copying every gossiped transaction a second time, but it isn't wired
+    /// up there yet: `NetworkableTransactionPool`/`Transaction` are defined in
+    /// `fuel-core-types` and have no lifetime parameter to borrow into, so
+    /// `RequestResponseMessageHandler::read_response` still decodes that variant
+    /// through the owned [`Self::decode_bounded`]. This method exists so that once
+    /// a borrowed response type exists, switching to it is a one-line change here
+    /// rather than new plumbing.
+    ///
+    /// Callers must keep `bytes` alive for as long as the returned value is used,
+    /// since `T`'s borrowed fields point directly into it.
+    pub fn decode_borrowed<'de, T>(&self, bytes: &'de [u8]) -> Result<T, io::Error>
+    where
+        T: serde::Deserialize<'de>,
+    {
+        let (value, remainder) = postcard::take_from_bytes(bytes)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        if !remainder.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "trailing bytes after borrowed decode",
+            ));
+        }
+        Ok(value)
+    }
+}
+
+impl PostcardCodec {
+    /// Like [`Decode::decode`], but rejects adversarial peers trying to exhaust
+    /// memory or CPU with implausibly large collections or embedded byte
+    /// strings: every sequence, map, and byte string's *declared* length is
+    /// checked against `limits` as it is read off the wire, before the real
+    /// `Vec`/`String` it becomes is ever allocated (see
+    /// [`bounded::decode_bounded`]).
+    pub fn decode_bounded<'de, T>(
+        &self,
+        bytes: &'de [u8],
+        limits: &DecodeLimits,
+    ) -> Result<T, BoundedDecodeError>
+    where
+        T: serde::Deserialize<'de>,
+    {
+        bounded::decode_bounded(bytes, limits)
+    }
+}
+
 #[cfg(test)]
 #[allow(non_snake_case)]
 mod tests {
+    use bytes::Bytes;
     use fuel_core_types::{
         blockchain::SealedBlockHeader,
         fuel_tx::Transaction,
@@ -112,7 +287,7 @@ mod tests {
             .expect("Valid Vec<SealedBlockHeader> should be serialized using v1");
 
         let deserialized = codec
-            .read_response(&RequestResponseProtocol::V2, &mut buf.as_slice())
+            .read_response(&RequestResponseProtocol::V2, Bytes::from(buf))
             .await
             .expect("Valid Vec<SealedBlockHeader> should be deserialized using v1");
 
@@ -143,7 +318,7 @@ mod tests {
             .expect("Valid full transactions should be serialized using v2");
 
         let deserialized = codec
-            .read_response(&RequestResponseProtocol::V2, &mut buf.as_slice())
+            .read_response(&RequestResponseProtocol::V2, Bytes::from(buf))
             .await
             .expect("Valid full transactions should be deserialized using v2");
 
@@ -171,7 +346,7 @@ mod tests {
             .expect("Valid Vec<SealedBlockHeader> should be serialized using v1");
 
         let deserialized = codec
-            .read_response(&RequestResponseProtocol::V1, &mut buf.as_slice())
+            .read_response(&RequestResponseProtocol::V1, Bytes::from(buf))
             .await
             .expect("Valid Vec<SealedBlockHeader> should be deserialized using v1");
 
@@ -199,7 +374,7 @@ mod tests {
             .expect("Valid Vec<SealedBlockHeader> is serialized using v1");
 
         let deserialized = codec
-            .read_response(&RequestResponseProtocol::V2, &mut buf.as_slice())
+            .read_response(&RequestResponseProtocol::V2, Bytes::from(buf))
             .await
             .expect("Valid Vec<SealedBlockHeader> is deserialized using v1");
 
@@ -230,7 +405,7 @@ mod tests {
             .expect("Valid Vec<SealedBlockHeader> is serialized using v1");
 
         let deserialized = codec
-            .read_response(&RequestResponseProtocol::V1, &mut buf.as_slice())
+            .read_response(&RequestResponseProtocol::V1, Bytes::from(buf))
             .await
             .expect("Valid Vec<SealedBlockHeader> is deserialized using v1");
 
@@ -271,6 +446,189 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn codec__decode_borrowed_avoids_copying_byte_slice_fields() {
+        // Given
+        #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+        struct Borrowed<'a> {
+            #[serde(borrow)]
+            payload: Cow<'a, [u8]>,
+        }
+        let codec = PostcardCodec;
+        let original = Borrowed {
+            payload: Cow::Owned(vec![1, 2, 3, 4]),
+        };
+        let encoded = codec.encode(&original).unwrap().into_owned();
+
+        // When
+        let decoded: Borrowed = codec
+            .decode_borrowed(&encoded)
+            .expect("borrowed decode should succeed");
+
+        // Then
+        assert!(matches!(decoded.payload, Cow::Borrowed(_)));
+        assert_eq!(decoded.payload.as_ref(), original.payload.as_ref());
+    }
+
+    #[test]
+    fn codec__decode_bounded_rejects_collection_exceeding_element_limit() {
+        // Given
+        let codec = PostcardCodec;
+        let sealed_block_headers = vec![SealedBlockHeader::default(); 4];
+        let response = V2ResponseMessage::SealedHeaders(Ok(sealed_block_headers));
+        let bytes = codec.encode(&response).unwrap().into_owned();
+        let limits = DecodeLimits {
+            max_elements: 3,
+            max_nested_bytes: bytes.len(),
+        };
+
+        // When
+        let result: Result<V2ResponseMessage, _> = codec.decode_bounded(&bytes, &limits);
+
+        // Then
+        assert!(matches!(
+            result,
+            Err(BoundedDecodeError::LimitExceeded {
+                kind: LimitKind::Elements,
+                limit: 3,
+                actual: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn codec__decode_bounded_accepts_response_within_limits() {
+        // Given
+        let codec = PostcardCodec;
+        let sealed_block_headers = vec![SealedBlockHeader::default(); 4];
+        let response = V2ResponseMessage::SealedHeaders(Ok(sealed_block_headers.clone()));
+        let bytes = codec.encode(&response).unwrap().into_owned();
+        let limits = DecodeLimits::from_max_response_size(MAX_REQUEST_SIZE);
+
+        // When
+        let decoded: V2ResponseMessage = codec
+            .decode_bounded(&bytes, &limits)
+            .expect("response within limits should decode");
+
+        // Then
+        assert!(matches!(
+            decoded,
+            V2ResponseMessage::SealedHeaders(Ok(headers)) if headers == sealed_block_headers
+        ));
+    }
+
+    #[tokio::test]
+    async fn codec__v3_roundtrip_through_write_and_read_response_leaves_small_responses_uncompressed()
+     {
+        // Given
+        let sealed_block_headers = vec![SealedBlockHeader::default()];
+        let response = V2ResponseMessage::SealedHeaders(Ok(sealed_block_headers.clone()));
+        let mut codec: RequestResponseMessageHandler<PostcardCodec> =
+            RequestResponseMessageHandler::new(MAX_REQUEST_SIZE);
+        let mut buf = Vec::with_capacity(1024);
+
+        // When
+        codec
+            .write_response(&RequestResponseProtocol::V3, &mut buf, response)
+            .await
+            .expect("Small V3 responses should encode");
+
+        // Then
+        let header = MessageHeader::from(&buf).expect("a V3 frame always has a header");
+        assert_eq!(header.flags & frame::flags::COMPRESSED, 0);
+        let deserialized = codec
+            .read_response(&RequestResponseProtocol::V3, Bytes::from(buf))
+            .await
+            .expect("Small V3 responses should decode");
+        assert!(matches!(
+            deserialized,
+            V2ResponseMessage::SealedHeaders(Ok(sealed_headers)) if sealed_headers == sealed_block_headers
+        ));
+    }
+
+    #[tokio::test]
+    async fn codec__v3_roundtrip_through_write_and_read_response_compresses_large_responses() {
+        // Given
+        let sealed_block_headers = vec![SealedBlockHeader::default(); 256];
+        let response = V2ResponseMessage::SealedHeaders(Ok(sealed_block_headers.clone()));
+        // Encoded (pre-compression) this response is bigger than
+        // `COMPRESSION_THRESHOLD_BYTES`, which is the point of the test, so it
+        // needs a `max_response_size` generous enough for its decompressed
+        // size to still pass the bound in `PostcardCodec::body_for_protocol`.
+        let max_response_size = NonZeroU32::new(1024 * 1024).unwrap();
+        let mut codec: RequestResponseMessageHandler<PostcardCodec> =
+            RequestResponseMessageHandler::new(max_response_size);
+        let mut buf = Vec::with_capacity(1024);
+
+        // When
+        codec
+            .write_response(&RequestResponseProtocol::V3, &mut buf, response)
+            .await
+            .expect("Large V3 responses should encode");
+
+        // Then
+        let header = MessageHeader::from(&buf).expect("a V3 frame always has a header");
+        assert_ne!(header.flags & frame::flags::COMPRESSED, 0);
+        let deserialized = codec
+            .read_response(&RequestResponseProtocol::V3, Bytes::from(buf))
+            .await
+            .expect("Large V3 responses should decode");
+        assert!(matches!(
+            deserialized,
+            V2ResponseMessage::SealedHeaders(Ok(sealed_headers)) if sealed_headers == sealed_block_headers
+        ));
+    }
+
+    #[tokio::test]
+    async fn codec__read_response_rejects_v3_frame_whose_decompressed_body_exceeds_max_response_size()
+     {
+        // Given: a highly-compressible response, so the compressed frame a
+        // peer sends is small, but what it decompresses to is not.
+        let sealed_block_headers = vec![SealedBlockHeader::default(); 256];
+        let response = V2ResponseMessage::SealedHeaders(Ok(sealed_block_headers));
+        let mut encoding_codec: RequestResponseMessageHandler<PostcardCodec> =
+            RequestResponseMessageHandler::new(NonZeroU32::new(1024 * 1024).unwrap());
+        let mut buf = Vec::with_capacity(1024);
+        encoding_codec
+            .write_response(&RequestResponseProtocol::V3, &mut buf, response)
+            .await
+            .expect("Large, compressible V3 responses should encode");
+        let header = MessageHeader::from(&buf).expect("a V3 frame always has a header");
+        assert_ne!(header.flags & frame::flags::COMPRESSED, 0);
+
+        // When: a peer with a much smaller configured `max_response_size`
+        // reads it back.
+        let mut codec: RequestResponseMessageHandler<PostcardCodec> =
+            RequestResponseMessageHandler::new(MAX_REQUEST_SIZE);
+        let result = codec.read_response(&RequestResponseProtocol::V3, Bytes::from(buf)).await;
+
+        // Then
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn codec__read_response_rejects_v3_frame_whose_body_is_shorter_than_its_header()
+     {
+        // Given
+        let response = V2ResponseMessage::SealedHeaders(Ok(vec![SealedBlockHeader::default()]));
+        let mut codec: RequestResponseMessageHandler<PostcardCodec> =
+            RequestResponseMessageHandler::new(MAX_REQUEST_SIZE);
+        let mut buf = Vec::with_capacity(1024);
+        codec
+            .write_response(&RequestResponseProtocol::V3, &mut buf, response)
+            .await
+            .unwrap();
+        buf.truncate(buf.len() - 1);
+
+        // When
+        let result = codec
+            .read_response(&RequestResponseProtocol::V3, Bytes::from(buf))
+            .await;
+
+        // Then
+        assert!(result.is_err());
+    }
+
     #[tokio::test]
     async fn codec__read_response_is_backwards_compatible_with_v1() {
         // Given
@@ -284,7 +642,7 @@ mod tests {
             .encode(&response)
             .expect("Serialization as V1ResponseMessage should succeed");
         let deserialized = codec
-            .read_response(&RequestResponseProtocol::V1, &mut &*buf)
+            .read_response(&RequestResponseProtocol::V1, Bytes::from(buf.into_owned()))
             .await
             .expect("Valid Vec<SealedBlockHeader> is deserialized using v1");
 
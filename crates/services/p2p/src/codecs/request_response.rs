@@ -0,0 +1,105 @@
+use super::{
+    Decode,
+    Encode,
+    bounded::DecodeLimits,
+    postcard::PostcardCodec,
+};
+use crate::request_response::{
+    messages::{
+        V1ResponseMessage,
+        V2ResponseMessage,
+    },
+    protocols::RequestResponseProtocol,
+};
+use bytes::Bytes;
+use std::{
+    io::{
+        self,
+        Write,
+    },
+    num::NonZeroU32,
+};
+
+/// Speaks the request/response protocol over whatever transport libp2p hands
+/// it: encodes/decodes message bodies via `Codec`, and adapts between the
+/// wire shape each negotiated [`RequestResponseProtocol`] version expects.
+#[derive(Clone)]
+pub struct RequestResponseMessageHandler<Codec> {
+    pub codec: Codec,
+    /// Configured response size limit, set by `new`. Used to derive the
+    /// collection-count limits for bounded decoding and to cap how large a
+    /// `V3` body may decompress to.
+    pub max_response_size: NonZeroU32,
+}
+
+impl RequestResponseMessageHandler<PostcardCodec> {
+    /// Writes `response` to `buf`, downgrading it to the wire shape the
+    /// negotiated `protocol` expects. `V1` peers only ever see the pre-`V2`
+    /// `Option`-shaped message, regardless of what error code `response`
+    /// carries. `V3` additionally gets opportunistic compression.
+    pub async fn write_response(
+        &mut self,
+        protocol: &RequestResponseProtocol,
+        buf: &mut Vec<u8>,
+        response: V2ResponseMessage,
+    ) -> io::Result<()> {
+        match protocol {
+            RequestResponseProtocol::V1 => {
+                let downgraded = V1ResponseMessage::from(response);
+                let encoded = self.codec.encode(&downgraded)?;
+                buf.write_all(encoded.as_ref())
+            }
+            RequestResponseProtocol::V2 => {
+                let encoded = self.codec.encode(&response)?;
+                buf.write_all(encoded.as_ref())
+            }
+            RequestResponseProtocol::V3 => {
+                let encoded = self.codec.encode_for_protocol(protocol, &response)?;
+                buf.write_all(&encoded)
+            }
+        }
+    }
+
+    /// Upgrades a response read off the wire into the current
+    /// [`V2ResponseMessage`], according to whatever shape the negotiated
+    /// `protocol` used.
+    ///
+    /// Takes `bytes` by value instead of a `&mut dyn Read` so the caller (the
+    /// libp2p codec glue, which already has to buffer the whole body before
+    /// it knows the length prefix is satisfied) can hand over the buffer it
+    /// already has instead of this method copying it again into a fresh
+    /// `Vec`. This does not, on its own, make decoding itself zero-copy: the
+    /// real message types (`V1ResponseMessage`/`V2ResponseMessage` and the
+    /// `NetworkableTransactionPool`/`Transaction` they're built from, defined
+    /// upstream in `fuel-core-types`) have no lifetime to borrow into, so
+    /// `decode`/`decode_bounded` below still allocate owned copies of every
+    /// field. [`PostcardCodec::decode_borrowed`] is ready for a borrowed
+    /// response type once one exists; wiring it up here too is blocked on
+    /// that upstream change, not on anything in this crate.
+    ///
+    /// `V1` responses are decoded with the plain, unbounded [`Decode::decode`],
+    /// including their `TxPoolFullTransactions` variant; `V2`/`V3` responses are decoded
+    /// through [`PostcardCodec::decode_bounded`] so a peer can't force an
+    /// oversized allocation via an inflated length prefix.
+    pub async fn read_response(
+        &mut self,
+        protocol: &RequestResponseProtocol,
+        bytes: Bytes,
+    ) -> io::Result<V2ResponseMessage> {
+        let limits = DecodeLimits::from_max_response_size(self.max_response_size);
+
+        match protocol {
+            RequestResponseProtocol::V1 => {
+                let v1: V1ResponseMessage = self.codec.decode(&bytes)?;
+                Ok(V2ResponseMessage::from(v1))
+            }
+            RequestResponseProtocol::V2 => Ok(self.codec.decode_bounded(&bytes, &limits)?),
+            RequestResponseProtocol::V3 => {
+                let body = self
+                    .codec
+                    .body_for_protocol(protocol, &bytes, self.max_response_size)?;
+                Ok(self.codec.decode_bounded(&body, &limits)?)
+            }
+        }
+    }
+}
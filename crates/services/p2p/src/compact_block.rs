@@ -0,0 +1,375 @@
+//! Compact block relay: a [`CompactBlock`] lets a node send a sketch of a
+//! block instead of every transaction in it, relying on the receiver's tx pool
+//! already holding most of them. [`reconstruct`] does the receiving side of
+//! this: it matches [`ShortTxId`]s against the pool and, for whatever it can't
+//! match, produces the [`GetBlockTxn`] follow-up request to fetch exactly
+//! those transactions.
+
+use fuel_core_types::{
+    blockchain::SealedBlockHeader,
+    fuel_tx::Transaction,
+    fuel_types::Bytes32,
+};
+use siphasher::sip::SipHasher24;
+use std::hash::Hasher;
+
+/// 6-byte SipHash-2-4 short ID, truncated like BIP152's `short_id`.
+pub type ShortTxId = [u8; 6];
+
+/// Per-block SipHash key, derived from the header hash plus a random nonce so
+/// that short IDs can't be precomputed by an adversary across blocks.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub struct ShortIdKey {
+    k0: u64,
+    k1: u64,
+}
+
+impl ShortIdKey {
+    pub fn derive(header_hash: &Bytes32, nonce: u64) -> Self {
+        let bytes = header_hash.as_ref();
+        let k0 = u64::from_le_bytes(bytes[0..8].try_into().expect("Bytes32 is 32 bytes"));
+        let k1 = u64::from_le_bytes(bytes[8..16].try_into().expect("Bytes32 is 32 bytes"))
+            ^ nonce;
+        Self { k0, k1 }
+    }
+
+    pub fn short_id(&self, transaction: &Transaction) -> ShortTxId {
+        let mut hasher = SipHasher24::new_with_keys(self.k0, self.k1);
+        hasher.write(&postcard::to_allocvec(transaction).unwrap_or_default());
+        let digest = hasher.finish().to_le_bytes();
+        let mut short_id = [0u8; 6];
+        short_id.copy_from_slice(&digest[0..6]);
+        short_id
+    }
+}
+
+/// A transaction sent in full alongside a [`CompactBlock`], identified by its
+/// index in the block's transaction list (e.g. the coinbase/mint is always
+/// prefilled since a receiver can never already have it in its pool).
+#[derive(Clone, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub struct PrefilledTransaction {
+    pub index: u16,
+    pub transaction: Transaction,
+}
+
+/// A BIP152-style sketch of a block: the header plus enough information for a
+/// receiver that already has most of the block's transactions in its tx pool
+/// to reconstruct it without re-downloading them.
+#[derive(Clone, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub struct CompactBlock {
+    pub header: SealedBlockHeader,
+    pub short_id_key: ShortIdKey,
+    pub prefilled: Vec<PrefilledTransaction>,
+    /// Short IDs for every transaction in the block that is *not* prefilled, in
+    /// block order.
+    pub short_ids: Vec<ShortTxId>,
+}
+
+/// A follow-up request for the transactions a receiver couldn't match against
+/// its own pool while reconstructing a [`CompactBlock`].
+#[derive(Clone, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub struct GetBlockTxn {
+    pub block_height_key: ShortIdKey,
+    /// Differentially-encoded indices of the missing transactions; see
+    /// [`encode_missing_indices`]/[`decode_missing_indices`].
+    pub missing_indices: Vec<u8>,
+}
+
+/// Differentially encodes a sorted, ascending list of transaction indices as
+/// varints, the same way BIP152's `getblocktxn` does: the first index is
+/// stored verbatim, and every subsequent index is stored as
+/// `index_i - index_{i-1} - 1`, which keeps the common case (consecutive
+/// missing indices) down to a single zero byte each.
+///
+/// Returns an error, rather than panicking, if `indices` is not sorted in
+/// strictly ascending order: `indices` is built from reconstruction results
+/// and ends up on the wire, so a bug or a confused match elsewhere in the
+/// reconstruction path must not be able to crash the node.
+pub fn encode_missing_indices(indices: &[u16]) -> Result<Vec<u8>, std::io::Error> {
+    let mut out = Vec::with_capacity(indices.len() * 2);
+    let mut previous: Option<u16> = None;
+    for &index in indices {
+        let delta = match previous {
+            None => index as u64,
+            Some(previous) => {
+                if index <= previous {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        "missing indices must be strictly ascending",
+                    ));
+                }
+                (index - previous - 1) as u64
+            }
+        };
+        write_varint(&mut out, delta);
+        previous = Some(index);
+    }
+    Ok(out)
+}
+
+/// Inverse of [`encode_missing_indices`]: accumulates `prev + delta + 1` for
+/// every varint after the first, which is taken verbatim.
+pub fn decode_missing_indices(mut bytes: &[u8]) -> Result<Vec<u16>, std::io::Error> {
+    let mut indices = Vec::new();
+    let mut previous: Option<u16> = None;
+    while !bytes.is_empty() {
+        let delta = read_varint(&mut bytes)?;
+        let index = match previous {
+            None => delta,
+            Some(previous) => previous as u64 + delta + 1,
+        };
+        let index = u16::try_from(index).map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "transaction index overflowed u16")
+        })?;
+        indices.push(index);
+        previous = Some(index);
+    }
+    Ok(indices)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let mut byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value != 0 {
+            byte |= 0x80;
+        }
+        out.push(byte);
+        if value == 0 {
+            break;
+        }
+    }
+}
+
+/// Outcome of [`reconstruct`]: either every short ID was matched against the
+/// local pool and the block is complete, or some weren't and the caller must
+/// send the returned [`GetBlockTxn`] to the peer that sent the [`CompactBlock`].
+#[derive(Debug)]
+pub enum Reconstructed {
+    Complete(Vec<Transaction>),
+    Missing(GetBlockTxn),
+}
+
+/// Matches `compact`'s short IDs against `pool_lookup` (typically the node's
+/// tx pool) to rebuild the block's transaction list without re-downloading
+/// anything the pool already has, mirroring BIP152's compact block
+/// reconstruction: every prefilled transaction is taken as-is, and every short
+/// ID is passed to `pool_lookup`.
+///
+/// `pool_lookup` returns the pool's transaction for a given short ID, or
+/// `None` if nothing in the pool hashes to it. `reconstruct` itself does not
+/// recompute or check the short ID of the transaction it gets back.
+pub fn reconstruct(
+    compact: &CompactBlock,
+    pool_lookup: impl Fn(&ShortTxId) -> Option<Transaction>,
+) -> Result<Reconstructed, std::io::Error> {
+    let total = compact.prefilled.len() + compact.short_ids.len();
+    let mut transactions: Vec<Option<Transaction>> = vec![None; total];
+    for prefilled in &compact.prefilled {
+        let index = prefilled.index as usize;
+        if index >= total {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "prefilled transaction index is out of range for this compact block",
+            ));
+        }
+        if transactions[index].is_some() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "prefilled transaction index is duplicated in this compact block",
+            ));
+        }
+        transactions[index] = Some(prefilled.transaction.clone());
+    }
+
+    let mut missing_indices = Vec::new();
+    let mut short_id_iter = compact.short_ids.iter();
+    for (index, slot) in transactions.iter_mut().enumerate() {
+        if slot.is_some() {
+            continue;
+        }
+        let short_id = short_id_iter.next().ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "compact block ran out of short IDs before index {index} was filled"
+                ),
+            )
+        })?;
+        match pool_lookup(short_id) {
+            Some(transaction) => *slot = Some(transaction),
+            None => missing_indices.push(index as u16),
+        }
+    }
+
+    if missing_indices.is_empty() {
+        let transactions = transactions
+            .into_iter()
+            .enumerate()
+            .map(|(index, transaction)| {
+                transaction.ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("compact block is missing a short ID for index {index}"),
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        return Ok(Reconstructed::Complete(transactions));
+    }
+
+    Ok(Reconstructed::Missing(GetBlockTxn {
+        block_height_key: compact.short_id_key,
+        missing_indices: encode_missing_indices(&missing_indices)?,
+    }))
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u64, std::io::Error> {
+    let mut value: u64 = 0;
+    let mut shift = 0;
+    loop {
+        let (&byte, rest) = bytes.split_first().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "truncated varint")
+        })?;
+        *bytes = rest;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+        shift += 7;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_indices__roundtrip_consecutive_run() {
+        let indices = vec![0, 1, 2, 3, 4];
+        let encoded = encode_missing_indices(&indices).unwrap();
+        let decoded = decode_missing_indices(&encoded).unwrap();
+        assert_eq!(decoded, indices);
+    }
+
+    #[test]
+    fn missing_indices__roundtrip_sparse() {
+        let indices = vec![2, 5, 6, 100, 1000];
+        let encoded = encode_missing_indices(&indices).unwrap();
+        let decoded = decode_missing_indices(&encoded).unwrap();
+        assert_eq!(decoded, indices);
+    }
+
+    #[test]
+    fn missing_indices__empty_roundtrips_to_empty() {
+        let encoded = encode_missing_indices(&[]).unwrap();
+        assert!(encoded.is_empty());
+        assert_eq!(decode_missing_indices(&encoded).unwrap(), Vec::<u16>::new());
+    }
+
+    #[test]
+    fn missing_indices__rejects_non_ascending_input_instead_of_panicking() {
+        let result = encode_missing_indices(&[5, 3]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn short_id_key__derive_is_deterministic_and_nonce_dependent() {
+        let header_hash = Bytes32::default();
+        let a = ShortIdKey::derive(&header_hash, 1);
+        let b = ShortIdKey::derive(&header_hash, 1);
+        let c = ShortIdKey::derive(&header_hash, 2);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn short_id__is_stable_for_the_same_key_and_transaction() {
+        let key = ShortIdKey::derive(&Bytes32::default(), 7);
+        let tx = Transaction::default_test_tx();
+        assert_eq!(key.short_id(&tx), key.short_id(&tx));
+    }
+
+    fn arbitrary_compact_block(transactions: &[Transaction]) -> CompactBlock {
+        let key = ShortIdKey::derive(&Bytes32::default(), 1);
+        CompactBlock {
+            header: SealedBlockHeader::default(),
+            short_id_key: key,
+            prefilled: vec![PrefilledTransaction {
+                index: 0,
+                transaction: transactions[0].clone(),
+            }],
+            short_ids: transactions[1..].iter().map(|tx| key.short_id(tx)).collect(),
+        }
+    }
+
+    #[test]
+    fn reconstruct__returns_complete_when_pool_has_every_transaction() {
+        // Given
+        let transactions = vec![Transaction::default_test_tx(), Transaction::default_test_tx()];
+        let compact = arbitrary_compact_block(&transactions);
+        let pool = transactions.clone();
+
+        // When
+        let outcome = reconstruct(&compact, |short_id| {
+            pool.iter()
+                .find(|tx| compact.short_id_key.short_id(tx) == *short_id)
+                .cloned()
+        })
+        .unwrap();
+
+        // Then
+        assert!(matches!(outcome, Reconstructed::Complete(txs) if txs == transactions));
+    }
+
+    #[test]
+    fn reconstruct__returns_get_block_txn_for_indices_missing_from_the_pool() {
+        // Given
+        let transactions = vec![Transaction::default_test_tx(), Transaction::default_test_tx()];
+        let compact = arbitrary_compact_block(&transactions);
+
+        // When: the pool has nothing besides the prefilled transaction.
+        let outcome = reconstruct(&compact, |_short_id| None).unwrap();
+
+        // Then
+        match outcome {
+            Reconstructed::Missing(get_block_txn) => {
+                assert_eq!(get_block_txn.block_height_key, compact.short_id_key);
+                assert_eq!(
+                    decode_missing_indices(&get_block_txn.missing_indices).unwrap(),
+                    vec![1]
+                );
+            }
+            Reconstructed::Complete(_) => panic!("expected a GetBlockTxn follow-up request"),
+        }
+    }
+
+    #[test]
+    fn reconstruct__rejects_duplicate_prefilled_indices() {
+        // Given: two prefilled entries both claim index 0, leaving only one
+        // short ID to cover what should be two remaining slots (1 and 2).
+        let key = ShortIdKey::derive(&Bytes32::default(), 1);
+        let compact = CompactBlock {
+            header: SealedBlockHeader::default(),
+            short_id_key: key,
+            prefilled: vec![
+                PrefilledTransaction {
+                    index: 0,
+                    transaction: Transaction::default_test_tx(),
+                },
+                PrefilledTransaction {
+                    index: 0,
+                    transaction: Transaction::default_test_tx(),
+                },
+            ],
+            short_ids: vec![[0u8; 6]],
+        };
+
+        // When
+        let result = reconstruct(&compact, |_short_id| None);
+
+        // Then
+        assert!(result.is_err());
+    }
+
+}
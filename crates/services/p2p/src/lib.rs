@@ -0,0 +1,3 @@
+pub mod codecs;
+pub mod compact_block;
+pub mod request_response;
@@ -0,0 +1,145 @@
+use crate::compact_block::{
+    CompactBlock,
+    GetBlockTxn,
+};
+use fuel_core_types::{
+    blockchain::SealedBlockHeader,
+    fuel_tx::Transaction,
+    services::p2p::NetworkableTransactionPool,
+};
+use std::ops::Range;
+
+/// A request sent to a peer. Carried alongside the negotiated
+/// [`super::protocols::RequestResponseProtocol`], which determines how the
+/// corresponding response is shaped.
+#[derive(Clone, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum RequestMessage {
+    SealedHeaders(Range<u32>),
+    Transactions(Range<u32>),
+    TxPoolAllTransactionsIds,
+    TxPoolFullTransactions(Vec<u32>),
+    /// Ask for a BIP152-style sketch of the block at this height instead of its
+    /// full transactions.
+    CompactBlock(u32),
+    /// Follow-up to an incomplete [`CompactBlock`] reconstruction, asking for
+    /// exactly the transactions the requester couldn't match in its own pool.
+    GetBlockTxn(GetBlockTxn),
+}
+
+/// Error codes a `V2`+ peer can report instead of an empty/missing response,
+/// so the requester can distinguish "you asked for something invalid" from
+/// "something went wrong on my end".
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum ResponseMessageErrorCode {
+    /// Placeholder used when downgrading a `V2`+ error to `V1`, which has no
+    /// error codes of its own and represents every failure as `None`.
+    ProtocolV1EmptyResponse,
+    RequestedRangeTooLarge,
+    Timeout,
+}
+
+/// Pre-`V2` response shape: every failure collapses to `None`, since `V1` peers
+/// have no way to report *why* a request failed.
+#[derive(Clone, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum V1ResponseMessage {
+    SealedHeaders(Option<Vec<SealedBlockHeader>>),
+    TxPoolFullTransactions(Option<Vec<Option<NetworkableTransactionPool>>>),
+}
+
+/// `V2`+ response shape: failures carry a [`ResponseMessageErrorCode`] instead
+/// of collapsing to `None`.
+#[derive(Clone, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum V2ResponseMessage {
+    SealedHeaders(Result<Vec<SealedBlockHeader>, ResponseMessageErrorCode>),
+    TxPoolFullTransactions(
+        Result<Vec<Option<NetworkableTransactionPool>>, ResponseMessageErrorCode>,
+    ),
+    /// Boxed because `CompactBlock` is much larger than every other variant's
+    /// payload; without this every response, including the common
+    /// `SealedHeaders`/`TxPoolFullTransactions` ones, would pay to move/store
+    /// it. `BlockTxn`'s `Vec<Transaction>` doesn't need the same treatment:
+    /// a `Vec` is already just a pointer/len/cap on the stack.
+    CompactBlock(Result<Box<CompactBlock>, ResponseMessageErrorCode>),
+    BlockTxn(Result<Vec<Transaction>, ResponseMessageErrorCode>),
+}
+
+/// Downgrades a `V2`+ response for a `V1` peer: every error, regardless of its
+/// original code, becomes `None`, since `V1` has nowhere to put the code.
+impl From<V2ResponseMessage> for V1ResponseMessage {
+    fn from(response: V2ResponseMessage) -> Self {
+        match response {
+            V2ResponseMessage::SealedHeaders(result) => {
+                V1ResponseMessage::SealedHeaders(result.ok())
+            }
+            V2ResponseMessage::TxPoolFullTransactions(result) => {
+                V1ResponseMessage::TxPoolFullTransactions(result.ok())
+            }
+            // `V1` predates compact block relay entirely; a `V1` peer should
+            // never be asked for one, but if it happens, report it the same
+            // way any other unsupported request would be.
+            V2ResponseMessage::CompactBlock(_) => {
+                V1ResponseMessage::SealedHeaders(None)
+            }
+            V2ResponseMessage::BlockTxn(_) => {
+                V1ResponseMessage::TxPoolFullTransactions(None)
+            }
+        }
+    }
+}
+
+/// Upgrades a response received from a `V1` peer: a missing value can't be
+/// distinguished from any particular failure, so it's reported as the generic
+/// [`ResponseMessageErrorCode::ProtocolV1EmptyResponse`].
+impl From<V1ResponseMessage> for V2ResponseMessage {
+    fn from(response: V1ResponseMessage) -> Self {
+        match response {
+            V1ResponseMessage::SealedHeaders(value) => V2ResponseMessage::SealedHeaders(
+                value.ok_or(ResponseMessageErrorCode::ProtocolV1EmptyResponse),
+            ),
+            V1ResponseMessage::TxPoolFullTransactions(value) => {
+                V2ResponseMessage::TxPoolFullTransactions(
+                    value.ok_or(ResponseMessageErrorCode::ProtocolV1EmptyResponse),
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn v2_to_v1__collapses_every_error_code_to_none() {
+        for code in [
+            ResponseMessageErrorCode::ProtocolV1EmptyResponse,
+            ResponseMessageErrorCode::RequestedRangeTooLarge,
+            ResponseMessageErrorCode::Timeout,
+        ] {
+            let downgraded: V1ResponseMessage =
+                V2ResponseMessage::SealedHeaders(Err(code)).into();
+            assert_eq!(downgraded, V1ResponseMessage::SealedHeaders(None));
+        }
+    }
+
+    #[test]
+    fn v1_to_v2__none_becomes_protocol_v1_empty_response() {
+        let upgraded: V2ResponseMessage =
+            V1ResponseMessage::SealedHeaders(None).into();
+        assert!(matches!(
+            upgraded,
+            V2ResponseMessage::SealedHeaders(Err(
+                ResponseMessageErrorCode::ProtocolV1EmptyResponse
+            ))
+        ));
+    }
+
+    #[test]
+    fn v1_to_v2_to_v1__roundtrips_a_successful_value() {
+        let headers = vec![SealedBlockHeader::default()];
+        let v1 = V1ResponseMessage::SealedHeaders(Some(headers.clone()));
+        let v2: V2ResponseMessage = v1.clone().into();
+        let back: V1ResponseMessage = v2.into();
+        assert_eq!(back, v1);
+    }
+}
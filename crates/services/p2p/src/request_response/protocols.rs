@@ -0,0 +1,33 @@
+/// The request/response wire protocol negotiated with a peer via libp2p's
+/// protocol-name multistream-select. Each variant is a distinct compatibility
+/// tier: peers that only understand `V1` must keep receiving exactly the bytes
+/// they always have, while newer peers negotiate up to whatever both sides
+/// support.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum RequestResponseProtocol {
+    /// Raw postcard, `Option`-shaped responses, no error codes.
+    V1,
+    /// Raw postcard, `Result`-shaped responses carrying a [`super::messages::ResponseMessageErrorCode`].
+    V2,
+    /// `V2`'s message shapes, framed behind a [`crate::codecs::frame::MessageHeader`]
+    /// and opportunistically zstd-compressed for large responses.
+    V3,
+}
+
+/// All protocol versions this node can speak, newest first, for use when
+/// advertising supported protocols during negotiation.
+pub const SUPPORTED_VERSIONS: [RequestResponseProtocol; 3] = [
+    RequestResponseProtocol::V3,
+    RequestResponseProtocol::V2,
+    RequestResponseProtocol::V1,
+];
+
+impl RequestResponseProtocol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequestResponseProtocol::V1 => "/fuel/req_res/0.0.1",
+            RequestResponseProtocol::V2 => "/fuel/req_res/0.0.2",
+            RequestResponseProtocol::V3 => "/fuel/req_res/0.0.3",
+        }
+    }
+}